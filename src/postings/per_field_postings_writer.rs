@@ -0,0 +1,90 @@
+use crate::postings::postings_writer::{JsonPostingsWriter, PostingsWriter, SpecializedPostingsWriter};
+use crate::postings::recorder::{DocIdRecorder, TFAndPositionRecorder};
+use crate::schema::{Field, FieldType, Schema};
+
+/// Owns one `PostingsWriter` per field in the schema, picking the writer
+/// implementation that matches each field's `FieldType`.
+pub(crate) struct PerFieldPostingsWriter {
+    per_field_postings_writers: Vec<Box<dyn PostingsWriter>>,
+}
+
+impl PerFieldPostingsWriter {
+    pub(crate) fn for_schema(schema: &Schema) -> PerFieldPostingsWriter {
+        let per_field_postings_writers = schema
+            .fields()
+            .map(|(_, field_entry)| posting_writer_from_field_type(field_entry.field_type()))
+            .collect();
+        PerFieldPostingsWriter {
+            per_field_postings_writers,
+        }
+    }
+
+    pub(crate) fn get_for_field(&self, field: Field) -> &dyn PostingsWriter {
+        &*self.per_field_postings_writers[field.field_id() as usize]
+    }
+
+    pub(crate) fn get_for_field_mut(&mut self, field: Field) -> &mut dyn PostingsWriter {
+        &mut *self.per_field_postings_writers[field.field_id() as usize]
+    }
+}
+
+/// Picks the `PostingsWriter` implementation for a given `FieldType`.
+///
+/// `JsonObject` gets a `JsonPostingsWriter`, so that string leaves keep full
+/// positions for phrase queries while numeric/boolean/date leaves only pay for
+/// doc-id postings. Every other indexed field keeps its previous writer.
+fn posting_writer_from_field_type(field_type: &FieldType) -> Box<dyn PostingsWriter> {
+    match field_type {
+        FieldType::Str(_) | FieldType::Facet(_) => {
+            SpecializedPostingsWriter::<TFAndPositionRecorder>::default().into()
+        }
+        FieldType::JsonObject(_) => {
+            JsonPostingsWriter::<TFAndPositionRecorder>::default().into()
+        }
+        FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_)
+        | FieldType::Bytes(_) => SpecializedPostingsWriter::<DocIdRecorder>::default().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::TopDocs;
+    use crate::query::{PhraseQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, STORED, TEXT};
+    use crate::{doc, Index, Term};
+
+    #[test]
+    fn test_json_field_indexes_string_and_numeric_leaves() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attrs = schema_builder.add_json_field("attrs", TEXT | STORED);
+        let schema = schema_builder.build();
+
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(attrs => serde_json::json!({
+            "description": "the quick brown fox",
+            "count": 3u64,
+        })))?;
+        index_writer.commit()?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+
+        let quick_term = Term::from_field_json_path(attrs, "description", false)
+            .with_type_and_str_value("quick");
+        let brown_term = Term::from_field_json_path(attrs, "description", false)
+            .with_type_and_str_value("brown");
+        let phrase_query = PhraseQuery::new(vec![quick_term, brown_term]);
+        let phrase_hits = searcher.search(&phrase_query, &TopDocs::with_limit(10))?;
+        assert_eq!(phrase_hits.len(), 1, "phrase query on the string leaf should match");
+
+        let count_term =
+            Term::from_field_json_path(attrs, "count", false).with_type_and_u64_value(3u64);
+        let term_query = TermQuery::new(count_term, IndexRecordOption::Basic);
+        let term_hits = searcher.search(&term_query, &TopDocs::with_limit(10))?;
+        assert_eq!(term_hits.len(), 1, "term query on the numeric leaf should match");
+
+        Ok(())
+    }
+}
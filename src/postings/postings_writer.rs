@@ -8,57 +8,114 @@ use super::stacker::{Addr, TermHashMap};
 use crate::fastfield::MultiValuedFastFieldWriter;
 use crate::fieldnorm::FieldNormReaders;
 use crate::indexer::doc_id_mapping::DocIdMapping;
-use crate::postings::recorder::{BufferLender, Recorder};
+use crate::postings::recorder::{BufferLender, DocIdRecorder, Recorder};
 use crate::postings::{
     FieldSerializer, IndexingContext, InvertedIndexSerializer, PerFieldPostingsWriter,
     UnorderedTermId,
 };
-use crate::schema::{Field, FieldType, Schema, Term};
+use crate::schema::{Field, FieldType, Schema, Term, Type, JSON_END_OF_PATH};
 use crate::termdict::TermOrdinal;
 use crate::tokenizer::{Token, TokenStream, MAX_TOKEN_LEN};
-use crate::DocId;
+use crate::{DocId, Executor};
 
 const POSITION_GAP: u32 = 1;
 
+/// The per-field outcome of term collection: the field's `PostingsWriter`, its terms
+/// sorted and ready to be handed to `PostingsWriter::serialize`, and the
+/// unordered-to-ordered term ordinal mapping for fields that need one (`Str`/`Facet`).
+type FieldTermPlan<'a> = (
+    Field,
+    &'a dyn PostingsWriter,
+    Vec<(Term<&'a [u8]>, Addr, UnorderedTermId)>,
+    Option<FnvHashMap<UnorderedTermId, TermOrdinal>>,
+);
+
+/// Below this many fields, dispatching to `executor` (thread-pool hand-off and
+/// result collection) costs more than just collecting terms on this thread, so
+/// `serialize_postings` ignores the caller-provided `executor` and runs sequentially.
+const PARALLEL_FIELD_THRESHOLD: usize = 4;
+
+fn should_collect_sequentially(field_count: usize) -> bool {
+    field_count < PARALLEL_FIELD_THRESHOLD
+}
+
+/// Collects and sorts one field's terms, along with the unordered-to-ordered term
+/// ordinal mapping it needs (if any). Pure w.r.t. `ctx`/`per_field_postings_writers`/
+/// `schema`, which is what lets `serialize_postings` run it either inline or fanned
+/// out over `executor`.
+fn collect_field_term_plan<'a>(
+    field: &Field,
+    ctx: &'a IndexingContext,
+    per_field_postings_writers: &'a PerFieldPostingsWriter,
+    schema: &Schema,
+) -> crate::Result<FieldTermPlan<'a>> {
+    let field = *field;
+    let postings_writer = per_field_postings_writers.get_for_field(field);
+    let term_offsets = postings_writer.term_offsets(ctx);
+
+    let field_entry = schema.get_field_entry(field);
+    let mapping = match *field_entry.field_type() {
+        FieldType::Str(_) | FieldType::Facet(_) => {
+            // populating the (unordered term ord) -> (ordered term ord) mapping
+            // for the field.
+            let unordered_term_ids = term_offsets.iter().map(|&(_, _, bucket)| bucket);
+            let mapping: FnvHashMap<UnorderedTermId, TermOrdinal> = unordered_term_ids
+                .enumerate()
+                .map(|(term_ord, unord_term_id)| {
+                    (unord_term_id as UnorderedTermId, term_ord as TermOrdinal)
+                })
+                .collect();
+            Some(mapping)
+        }
+        FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_) => None,
+        FieldType::Bytes(_) => None,
+        FieldType::JsonObject(_) => None,
+    };
+
+    Ok((field, postings_writer, term_offsets, mapping))
+}
+
 /// Serialize the inverted index.
 /// It pushes all term, one field at a time, towards the
 /// postings serializer.
+///
+/// Collecting and sorting each field's terms only reads shared immutable state
+/// (`ctx`, `per_field_postings_writers`, `schema`), so that part of the work is
+/// fanned out over `executor` — except for schemas with fewer than
+/// `PARALLEL_FIELD_THRESHOLD` fields, where it always runs sequentially on this
+/// thread regardless of what `executor` the caller passed in, since spinning up a
+/// pool would outweigh the gains. The actual writes into `serializer` (which also
+/// consume `fieldnorm_readers` and `doc_id_map`) stay on this thread and run in
+/// field order, since `InvertedIndexSerializer` must see fields appended in a
+/// stable order.
 pub(crate) fn serialize_postings(
     ctx: IndexingContext,
     per_field_postings_writers: &PerFieldPostingsWriter,
     fieldnorm_readers: FieldNormReaders,
     doc_id_map: Option<&DocIdMapping>,
     schema: &Schema,
+    executor: &Executor,
     serializer: &mut InvertedIndexSerializer,
 ) -> crate::Result<HashMap<Field, FnvHashMap<UnorderedTermId, TermOrdinal>>> {
     let mut unordered_term_mappings: HashMap<Field, FnvHashMap<UnorderedTermId, TermOrdinal>> =
         HashMap::new();
 
-    for (field, _) in schema.fields() {
-        let postings_writer = per_field_postings_writers.get_for_field(field);
+    let fields: Vec<Field> = schema.fields().map(|(field, _)| field).collect();
+    let field_plans: Vec<FieldTermPlan> = if should_collect_sequentially(fields.len()) {
+        fields
+            .iter()
+            .map(|field| collect_field_term_plan(field, &ctx, per_field_postings_writers, schema))
+            .collect::<crate::Result<_>>()?
+    } else {
+        executor.map(
+            |field| collect_field_term_plan(field, &ctx, per_field_postings_writers, schema),
+            fields.iter(),
+        )?
+    };
 
-        let mut term_offsets: Vec<(Term<&[u8]>, Addr, UnorderedTermId)> =
-            Vec::with_capacity(postings_writer.term_map().len());
-        term_offsets.extend(postings_writer.term_map().iter(&ctx.arena_terms));
-        term_offsets.sort_unstable_by_key(|(k, _, _)| k.clone());
-
-        let field_entry = schema.get_field_entry(field);
-        match *field_entry.field_type() {
-            FieldType::Str(_) | FieldType::Facet(_) => {
-                // populating the (unordered term ord) -> (ordered term ord) mapping
-                // for the field.
-                let unordered_term_ids = term_offsets.iter().map(|&(_, _, bucket)| bucket);
-                let mapping: FnvHashMap<UnorderedTermId, TermOrdinal> = unordered_term_ids
-                    .enumerate()
-                    .map(|(term_ord, unord_term_id)| {
-                        (unord_term_id as UnorderedTermId, term_ord as TermOrdinal)
-                    })
-                    .collect();
-                unordered_term_mappings.insert(field, mapping);
-            }
-            FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_) => {}
-            FieldType::Bytes(_) => {}
-            FieldType::JsonObject(_) => {}
+    for (field, postings_writer, term_offsets, mapping) in field_plans {
+        if let Some(mapping) = mapping {
+            unordered_term_mappings.insert(field, mapping);
         }
 
         let fieldnorm_reader = fieldnorm_readers.get_field(field)?;
@@ -70,6 +127,27 @@ pub(crate) fn serialize_postings(
     Ok(unordered_term_mappings)
 }
 
+// `serialize_postings` runs end to end on every index commit (e.g. the JSON field test
+// in `per_field_postings_writer.rs`), but those schemas have too few fields to reach
+// the executor; the sequential-vs-parallel choice it makes is covered directly here.
+#[cfg(test)]
+mod executor_heuristic_tests {
+    use super::*;
+
+    #[test]
+    fn test_small_schemas_stay_sequential() {
+        assert!(should_collect_sequentially(0));
+        assert!(should_collect_sequentially(1));
+        assert!(should_collect_sequentially(PARALLEL_FIELD_THRESHOLD - 1));
+    }
+
+    #[test]
+    fn test_large_schemas_use_the_executor() {
+        assert!(!should_collect_sequentially(PARALLEL_FIELD_THRESHOLD));
+        assert!(!should_collect_sequentially(PARALLEL_FIELD_THRESHOLD + 10));
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct IndexingPosition {
     pub num_tokens: u32,
@@ -80,7 +158,11 @@ pub(crate) struct IndexingPosition {
 /// and building a `Segment` in anonymous memory.
 ///
 /// `PostingsWriter` writes in a `MemoryArena`.
-pub(crate) trait PostingsWriter {
+///
+/// `Sync` so that `&dyn PostingsWriter` can be handed out to worker threads by
+/// `serialize_postings`'s `Executor::map` call without the compiler rejecting the
+/// cross-thread share.
+pub(crate) trait PostingsWriter: Sync {
     /// Record that a document contains a term at a given position.
     ///
     /// * doc  - the document id
@@ -98,7 +180,16 @@ pub(crate) trait PostingsWriter {
 
     fn mem_usage(&self) -> usize;
 
-    fn term_map(&self) -> &TermHashMap;
+    /// Returns the terms this writer holds, sorted lexicographically, together with
+    /// their arena address and unordered term id.
+    ///
+    /// Writers backed by more than one `TermHashMap` (e.g. `JsonPostingsWriter`) merge
+    /// their sub-maps into a single ordered stream here; there is no general-purpose
+    /// `term_map()` accessor, since it would have no sane single-map answer for them.
+    fn term_offsets<'a>(
+        &'a self,
+        ctx: &'a IndexingContext,
+    ) -> Vec<(Term<&'a [u8]>, Addr, UnorderedTermId)>;
 
     /// Serializes the postings on disk.
     /// The actual serialization format is handled by the `PostingsSerializer`.
@@ -163,7 +254,7 @@ pub(crate) struct SpecializedPostingsWriter<Rec: Recorder> {
     pub(crate) term_map: TermHashMap,
 }
 
-impl<Rec: Recorder> From<SpecializedPostingsWriter<Rec>> for Box<dyn PostingsWriter> {
+impl<Rec: Recorder + Sync> From<SpecializedPostingsWriter<Rec>> for Box<dyn PostingsWriter> {
     fn from(
         specialized_postings_writer: SpecializedPostingsWriter<Rec>,
     ) -> Box<dyn PostingsWriter> {
@@ -191,13 +282,20 @@ impl<Rec: Recorder> SpecializedPostingsWriter<Rec> {
     }
 }
 
-impl<Rec: Recorder> PostingsWriter for SpecializedPostingsWriter<Rec> {
+impl<Rec: Recorder + Sync> PostingsWriter for SpecializedPostingsWriter<Rec> {
     fn mem_usage(&self) -> usize {
         self.term_map.mem_usage()
     }
 
-    fn term_map(&self) -> &TermHashMap {
-        &self.term_map
+    fn term_offsets<'a>(
+        &'a self,
+        ctx: &'a IndexingContext,
+    ) -> Vec<(Term<&'a [u8]>, Addr, UnorderedTermId)> {
+        let mut term_offsets: Vec<(Term<&[u8]>, Addr, UnorderedTermId)> =
+            Vec::with_capacity(self.term_map.len());
+        term_offsets.extend(self.term_map.iter(&ctx.arena_terms));
+        term_offsets.sort_unstable_by_key(|(k, _, _)| k.clone());
+        term_offsets
     }
 
     fn subscribe(
@@ -259,3 +357,136 @@ impl<Rec: Recorder> PostingsWriter for SpecializedPostingsWriter<Rec> {
         self.total_num_tokens
     }
 }
+
+/// Splits the value bytes of a JSON term into its path prefix, the leaf's `Type`,
+/// and the bytes encoding the leaf value itself.
+///
+/// JSON terms are encoded as `path | JSON_END_OF_PATH | type_code | value`, which is
+/// what lets `JsonPostingsWriter` decide, for a given term, whether it belongs to the
+/// tokenized string postings or the doc-id-only postings. Returns `None` when `bytes`
+/// doesn't match that shape (missing end-of-path marker, or a type code `Type` doesn't
+/// recognize) instead of panicking, so that a term we can't interpret is skipped rather
+/// than aborting the whole commit.
+fn as_json_path_type_value_bytes(bytes: &[u8]) -> Option<(&[u8], Type, &[u8])> {
+    let pos = bytes.iter().position(|&b| b == JSON_END_OF_PATH)?;
+    let json_path_bytes = &bytes[..pos];
+    let type_code = *bytes.get(pos + 1)?;
+    let typ = Type::from_code(type_code)?;
+    let value_bytes = &bytes[pos + 2..];
+    Some((json_path_bytes, typ, value_bytes))
+}
+
+/// `PostingsWriter` for `FieldType::JsonObject`.
+///
+/// A JSON document can nest arbitrarily typed leaves under the same field, and the
+/// two kinds of leaves need different posting list shapes: tokenized string values
+/// need full positions for phrase queries, while numeric/boolean/date leaves only
+/// need to record which documents contain them. `JsonPostingsWriter` keeps one
+/// `SpecializedPostingsWriter` of each kind and merges their term streams back
+/// together at serialization time, so the field still exposes a single, ordered
+/// term dictionary.
+#[derive(Default)]
+pub(crate) struct JsonPostingsWriter<Rec: Recorder> {
+    str_posting_writer: SpecializedPostingsWriter<Rec>,
+    non_str_posting_writer: SpecializedPostingsWriter<DocIdRecorder>,
+}
+
+impl<Rec: Recorder + Sync> From<JsonPostingsWriter<Rec>> for Box<dyn PostingsWriter> {
+    fn from(json_postings_writer: JsonPostingsWriter<Rec>) -> Box<dyn PostingsWriter> {
+        Box::new(json_postings_writer)
+    }
+}
+
+impl<Rec: Recorder + Sync> PostingsWriter for JsonPostingsWriter<Rec> {
+    fn mem_usage(&self) -> usize {
+        self.str_posting_writer.mem_usage() + self.non_str_posting_writer.mem_usage()
+    }
+
+    fn term_offsets<'a>(
+        &'a self,
+        ctx: &'a IndexingContext,
+    ) -> Vec<(Term<&'a [u8]>, Addr, UnorderedTermId)> {
+        let mut term_offsets: Vec<(Term<&[u8]>, Addr, UnorderedTermId)> = Vec::with_capacity(
+            self.str_posting_writer.term_map.len() + self.non_str_posting_writer.term_map.len(),
+        );
+        term_offsets.extend(self.str_posting_writer.term_map.iter(&ctx.arena_terms));
+        term_offsets.extend(self.non_str_posting_writer.term_map.iter(&ctx.arena_terms));
+        term_offsets.sort_unstable_by_key(|(k, _, _)| k.clone());
+        term_offsets
+    }
+
+    fn subscribe(
+        &mut self,
+        doc: DocId,
+        pos: u32,
+        term: &Term,
+        ctx: &mut IndexingContext,
+    ) -> UnorderedTermId {
+        // Only non-string leaves reach `subscribe` directly: string leaves are
+        // tokenized and routed to the str writer by `index_text` below.
+        self.non_str_posting_writer.subscribe(doc, pos, term, ctx)
+    }
+
+    fn serialize(
+        &self,
+        term_addrs: &[(Term<&[u8]>, Addr, UnorderedTermId)],
+        doc_id_map: Option<&DocIdMapping>,
+        ctx: &IndexingContext,
+        serializer: &mut FieldSerializer,
+    ) -> io::Result<()> {
+        let mut buffer_lender = BufferLender::default();
+        for (term, addr, _) in term_addrs {
+            let Some((_path, typ, _value)) = as_json_path_type_value_bytes(term.value_bytes())
+            else {
+                // Can't interpret this term's encoding (stray/legacy bytes, or a type
+                // code we don't recognize); skip it rather than aborting the commit.
+                continue;
+            };
+            if typ == Type::Str {
+                SpecializedPostingsWriter::<Rec>::serialize_one_term(
+                    term,
+                    *addr,
+                    doc_id_map,
+                    &mut buffer_lender,
+                    ctx,
+                    &self.str_posting_writer.term_map,
+                    serializer,
+                )?;
+            } else {
+                SpecializedPostingsWriter::<DocIdRecorder>::serialize_one_term(
+                    term,
+                    *addr,
+                    doc_id_map,
+                    &mut buffer_lender,
+                    ctx,
+                    &self.non_str_posting_writer.term_map,
+                    serializer,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn index_text(
+        &mut self,
+        doc_id: DocId,
+        token_stream: &mut dyn TokenStream,
+        term_buffer: &mut Term,
+        ctx: &mut IndexingContext,
+        indexing_position: &mut IndexingPosition,
+        term_id_fast_field_writer_opt: Option<&mut MultiValuedFastFieldWriter>,
+    ) {
+        self.str_posting_writer.index_text(
+            doc_id,
+            token_stream,
+            term_buffer,
+            ctx,
+            indexing_position,
+            term_id_fast_field_writer_opt,
+        );
+    }
+
+    fn total_num_tokens(&self) -> u64 {
+        self.str_posting_writer.total_num_tokens() + self.non_str_posting_writer.total_num_tokens()
+    }
+}